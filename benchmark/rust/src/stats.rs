@@ -1,9 +1,5 @@
-/// Log2-scaled histogram buckets in microseconds.
-/// Buckets: [0,1), [1,2), [2,4), [4,8), [8,16), [16,32), [32,64), [64,128), [128+)
-pub const NUM_BUCKETS: usize = 9;
-pub const BUCKET_LABELS: [&str; NUM_BUCKETS] = [
-    " <1 ", "  1 ", "  2 ", "  4 ", "  8 ", " 16 ", " 32 ", " 64 ", "128+",
-];
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
 
 #[derive(Clone, Default)]
 pub struct StatResult {
@@ -13,14 +9,218 @@ pub struct StatResult {
     pub min: u64,
     pub max: u64,
     pub p50: u64,
+    pub p90: u64,
     pub p99: u64,
+    pub p999: u64,
     pub count: usize,
+    /// Median absolute deviation: `1.4826 * median(|xᵢ − median|)`. The
+    /// 1.4826 factor makes this consistent with σ for normally distributed
+    /// data, and it is far less outlier-sensitive than `stddev`.
+    pub mad: f64,
+    /// `mad / median`, a scale-free dispersion measure so runs with
+    /// different absolute latencies stay comparable.
+    pub mad_rel: f64,
+    /// Underlying distribution, kept so [`StatResult::merge`] can recompute
+    /// percentiles from the combined samples instead of averaging them.
+    pub histogram: Histogram,
 }
 
-#[derive(Clone, Default)]
+/// Winsorization level used as the default basis for `trimmed_mean`: samples
+/// below the 5th percentile are clamped up to it, and samples above the 95th
+/// are clamped down to it, before averaging. This bounds the influence of
+/// extreme scheduler stalls instead of discarding that tail mass outright.
+const DEFAULT_WINSORIZE_PCT: f64 = 5.0;
+
+/// Interpolated percentile over a sorted slice, matching the convention used
+/// by the Rust `test` crate and plotters' `Quartiles`.
+///
+/// `sorted` must be non-empty and sorted ascending. `pct` is in `[0, 100]`.
+pub fn percentile(sorted: &[u64], pct: f64) -> f64 {
+    assert!(!sorted.is_empty(), "percentile of empty sample set");
+    let n = sorted.len();
+    if n == 1 {
+        return sorted[0] as f64;
+    }
+    if pct == 100.0 {
+        return sorted[n - 1] as f64;
+    }
+    let rank = (pct / 100.0) * (n - 1) as f64;
+    let lo_idx = rank.floor() as usize;
+    let d = rank - rank.floor();
+    let lo = sorted[lo_idx] as f64;
+    let hi = sorted[lo_idx + 1] as f64;
+    lo + (hi - lo) * d
+}
+
+/// Interpolated percentile of an already-sorted slice of absolute
+/// deviations, using the same rank convention as [`percentile`].
+fn percentile_f64(sorted: &[f64], pct: f64) -> f64 {
+    let n = sorted.len();
+    if n == 1 {
+        return sorted[0];
+    }
+    if pct == 100.0 {
+        return sorted[n - 1];
+    }
+    let rank = (pct / 100.0) * (n - 1) as f64;
+    let lo_idx = rank.floor() as usize;
+    let d = rank - rank.floor();
+    sorted[lo_idx] + (sorted[lo_idx + 1] - sorted[lo_idx]) * d
+}
+
+/// Median absolute deviation of `sorted` around `median`, scaled by 1.4826
+/// so it is consistent with `stddev` under normality. See the Rust `test`
+/// crate's `Stats::median_abs_dev`.
+fn median_abs_dev(sorted: &[u64], median: f64) -> f64 {
+    let mut dev: Vec<f64> = sorted.iter().map(|&v| (v as f64 - median).abs()).collect();
+    dev.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    1.4826 * percentile_f64(&dev, 50.0)
+}
+
+/// Clamps every sample below `percentile(pct)` up to that value and every
+/// sample above `percentile(100-pct)` down to it, then averages. Bounds the
+/// influence of tail outliers without discarding them outright.
+///
+/// `pct` is the fraction clamped off *each* tail, so it must be in `[0, 50]`
+/// (a larger value would flip `lo` above `hi`, the two tails crossing).
+pub fn winsorized_mean(sorted: &[u64], pct: f64) -> f64 {
+    assert!(
+        (0.0..=50.0).contains(&pct),
+        "winsorize pct must be in [0, 50], got {pct}"
+    );
+    let lo = percentile(sorted, pct);
+    let hi = percentile(sorted, 100.0 - pct);
+    sorted
+        .iter()
+        .map(|&v| (v as f64).clamp(lo, hi))
+        .sum::<f64>()
+        / sorted.len() as f64
+}
+
+/// Configures a [`Histogram`]'s resolution and tracked range in place of
+/// compile-time constants: `precision` sub-buckets per e-fold increase in
+/// value (bounds relative percentile error to roughly `1 / (2*precision)`),
+/// over `[min_ns, max_ns]`. Samples outside the range are clamped into the
+/// nearest edge bucket. A user measuring sub-microsecond wakeups can narrow
+/// the range and raise `precision`; one measuring millisecond stalls can
+/// widen `max_ns` instead of saturating a fixed top bucket.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HistogramConfig {
+    pub precision: f64,
+    pub min_ns: u64,
+    pub max_ns: u64,
+}
+
+impl Default for HistogramConfig {
+    /// ~0.5% bucket error from 1ns to `u64::MAX` nanoseconds.
+    fn default() -> Self {
+        Self {
+            precision: 100.0,
+            min_ns: 1,
+            max_ns: u64::MAX,
+        }
+    }
+}
+
+impl HistogramConfig {
+    /// Resolves this config into its bucket geometry once, instead of
+    /// recomputing `ln()`/`ceil()` on every [`Histogram::record`] call.
+    fn resolve(self) -> BucketGeometry {
+        BucketGeometry::new(self)
+    }
+
+    /// Human-readable label for each bucket, generated from this config's
+    /// range and precision rather than a fixed table.
+    pub fn labels(&self) -> Vec<String> {
+        self.resolve().labels()
+    }
+}
+
+/// A [`HistogramConfig`] resolved into the derived values its hot path
+/// needs — `min_ln` and `bucket_count` each involve a `ln()`/`ceil()`, so
+/// they're computed once here rather than per recorded sample.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct BucketGeometry {
+    config: HistogramConfig,
+    min_ln: f64,
+    bucket_count: usize,
+}
+
+impl BucketGeometry {
+    fn new(config: HistogramConfig) -> Self {
+        let min_ln = (config.min_ns.max(1) as f64).ln();
+        let span = (config.max_ns.max(config.min_ns) as f64).ln() - min_ln;
+        let bucket_count = (span * config.precision).ceil() as usize + 1;
+        Self {
+            config,
+            min_ln,
+            bucket_count,
+        }
+    }
+
+    fn bucket_index(&self, ns: u64) -> usize {
+        let v = ns.clamp(
+            self.config.min_ns,
+            self.config.max_ns.max(self.config.min_ns),
+        ) as f64;
+        let idx = (v.ln() - self.min_ln) * self.config.precision;
+        (idx.round() as usize).min(self.bucket_count - 1)
+    }
+
+    fn bucket_value(&self, idx: usize) -> u64 {
+        (self.min_ln + idx as f64 / self.config.precision)
+            .exp()
+            .round() as u64
+    }
+
+    fn labels(&self) -> Vec<String> {
+        (0..self.bucket_count)
+            .map(|idx| format_ns(self.bucket_value(idx)))
+            .collect()
+    }
+}
+
+fn format_ns(ns: u64) -> String {
+    if ns < 1_000 {
+        format!("{ns}ns")
+    } else if ns < 1_000_000 {
+        format!("{}µs", ns / 1_000)
+    } else if ns < 1_000_000_000 {
+        format!("{}ms", ns / 1_000_000)
+    } else {
+        format!("{}s", ns / 1_000_000_000)
+    }
+}
+
+/// Log2-scaled display buckets in microseconds, independent of whatever
+/// [`HistogramConfig`] a [`Histogram`] was built with. Configurable
+/// `precision` gives a `Histogram` thousands of raw buckets by default —
+/// too many rows for the TUI — so [`Histogram::coarse_fraction`] downsamples
+/// into this small, fixed set for rendering instead.
+pub const NUM_COARSE_BUCKETS: usize = 9;
+pub const COARSE_BUCKET_LABELS: [&str; NUM_COARSE_BUCKETS] = [
+    " <1 ", "  1 ", "  2 ", "  4 ", "  8 ", " 16 ", " 32 ", " 64 ", "128+",
+];
+
+/// Upper bound (exclusive), in microseconds, of each coarse display bucket.
+/// The final bucket (`128+`) has no upper bound.
+const COARSE_BOUNDS_US: [u64; NUM_COARSE_BUCKETS - 1] = [1, 2, 4, 8, 16, 32, 64, 128];
+
+/// Log-scaled histogram of nanosecond latencies with bounded (~0.5%)
+/// relative percentile error, following the historian/rio bucketing
+/// approach. Unlike a fixed linear histogram, this recovers percentiles at
+/// any resolution and merges cheaply by summing counters.
+#[derive(Clone)]
 pub struct Histogram {
-    pub buckets: [u32; NUM_BUCKETS],
-    pub total: u32,
+    geometry: BucketGeometry,
+    counts: Vec<u64>,
+    pub total: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::with_config(HistogramConfig::default())
+    }
 }
 
 impl StatResult {
@@ -32,8 +232,10 @@ impl StatResult {
         let n = samples.len();
         let min = samples[0];
         let max = samples[n - 1];
-        let p50 = samples[n / 2];
-        let p99 = samples[((n - 1) as f64 * 0.99) as usize];
+        let p50 = percentile(samples, 50.0) as u64;
+        let p90 = percentile(samples, 90.0) as u64;
+        let p99 = percentile(samples, 99.0) as u64;
+        let p999 = percentile(samples, 99.9) as u64;
 
         let sum: f64 = samples.iter().map(|&v| v as f64).sum();
         let mean = sum / n as f64;
@@ -51,24 +253,9 @@ impl StatResult {
             0.0
         };
 
-        // IQR-based outlier removal: filter values outside Q1 - 3*IQR to Q3 + 3*IQR
-        let q1_idx = n / 4;
-        let q3_idx = 3 * n / 4;
-        let q1 = samples[q1_idx] as f64;
-        let q3 = samples[q3_idx] as f64;
-        let iqr = q3 - q1;
-        let lower = (q1 - 3.0 * iqr).max(0.0) as u64;
-        let upper = (q3 + 3.0 * iqr) as u64;
-        let filtered: Vec<u64> = samples
-            .iter()
-            .filter(|&&v| v >= lower && v <= upper)
-            .copied()
-            .collect();
-        let trimmed_mean = if !filtered.is_empty() {
-            filtered.iter().map(|&v| v as f64).sum::<f64>() / filtered.len() as f64
-        } else {
-            mean
-        };
+        let trimmed_mean = winsorized_mean(samples, DEFAULT_WINSORIZE_PCT);
+        let mad = median_abs_dev(samples, p50 as f64);
+        let mad_rel = if p50 > 0 { mad / p50 as f64 } else { 0.0 };
 
         Self {
             mean,
@@ -77,33 +264,136 @@ impl StatResult {
             min,
             max,
             p50,
+            p90,
             p99,
+            p999,
             count: n,
+            mad,
+            mad_rel,
+            histogram: Histogram::from_samples(samples),
         }
     }
 
+    /// Aggregates sub-results by summing their histograms and recomputing
+    /// percentiles from the sum rather than averaging them: the mean of
+    /// several medians is not the median. `min`/`max` are the true extremes;
+    /// `mean`, `trimmed_mean` and `mad` are count-weighted averages of the
+    /// per-run values.
     pub fn merge(results: &[StatResult]) -> Self {
         if results.is_empty() {
             return Self::default();
         }
-        let n = results.len() as f64;
-        let mean = results.iter().map(|r| r.mean).sum::<f64>() / n;
-        let trimmed_mean = results.iter().map(|r| r.trimmed_mean).sum::<f64>() / n;
-        let stddev = (results.iter().map(|r| r.stddev * r.stddev).sum::<f64>() / n).sqrt();
-        let min = results.iter().map(|r| r.min).min().unwrap_or(0);
-        let max = results.iter().map(|r| r.max).max().unwrap_or(0);
-        let p50 = (results.iter().map(|r| r.p50 as f64).sum::<f64>() / n) as u64;
-        let p99 = (results.iter().map(|r| r.p99 as f64).sum::<f64>() / n) as u64;
-        let count = results.iter().map(|r| r.count).sum();
+        // Seed from the first result's geometry rather than
+        // `Histogram::default()`: callers with a non-default `HistogramConfig`
+        // (e.g. via `stats::init`) would otherwise panic in `Histogram::merge`,
+        // which asserts both sides share the same bucket geometry.
+        let mut histogram = Histogram::with_config(results[0].histogram.config());
+        for r in results {
+            histogram.merge(&r.histogram);
+        }
+
+        // Zero-count sub-results (e.g. an idle thread's empty histogram) carry
+        // min == max == 0 from `Self::default()` and must not drag the
+        // aggregate down; only non-empty sub-results have a meaningful bound.
+        let min = results
+            .iter()
+            .filter(|r| r.count > 0)
+            .map(|r| r.min)
+            .min()
+            .unwrap_or(0);
+        let max = results
+            .iter()
+            .filter(|r| r.count > 0)
+            .map(|r| r.max)
+            .max()
+            .unwrap_or(0);
+        let p50 = histogram.value_at_percentile(50.0);
+        let p90 = histogram.value_at_percentile(90.0);
+        let p99 = histogram.value_at_percentile(99.0);
+        let p999 = histogram.value_at_percentile(99.9);
+        let count: usize = results.iter().map(|r| r.count).sum();
+
+        // Count-weighted mean, not an unweighted average of per-run means.
+        let mean = results.iter().map(|r| r.mean * r.count as f64).sum::<f64>() / count as f64;
+        let trimmed_mean = results
+            .iter()
+            .map(|r| r.trimmed_mean * r.count as f64)
+            .sum::<f64>()
+            / count as f64;
+
+        // Pooled variance: within-run variance plus between-run mean spread.
+        let within: f64 = results
+            .iter()
+            .map(|r| (r.count as f64 - 1.0) * r.stddev * r.stddev)
+            .sum();
+        let between: f64 = results
+            .iter()
+            .map(|r| r.count as f64 * (r.mean - mean) * (r.mean - mean))
+            .sum();
+        let var = if count > 1 {
+            (within + between) / (count as f64 - 1.0)
+        } else {
+            0.0
+        };
+
+        // MAD isn't additively mergeable like the histogram, so approximate
+        // it as a count-weighted average of the per-run MADs.
+        let mad = results.iter().map(|r| r.mad * r.count as f64).sum::<f64>() / count as f64;
+        let mad_rel = if p50 > 0 { mad / p50 as f64 } else { 0.0 };
+
         Self {
             mean,
             trimmed_mean,
-            stddev,
+            stddev: var.sqrt(),
             min,
             max,
             p50,
+            p90,
             p99,
+            p999,
             count,
+            mad,
+            mad_rel,
+            histogram,
+        }
+    }
+
+    /// Builds a result from a merged histogram rather than raw samples, e.g.
+    /// the shared global histogram that per-thread [`record`] calls fold
+    /// into. Percentiles carry the histogram's usual ~0.5% bucket error;
+    /// `mean`/`stddev`/`mad` are further approximated from bucket midpoints,
+    /// since the histogram doesn't retain exact sample values.
+    pub fn from_histogram(histogram: &Histogram) -> Self {
+        if histogram.total == 0 {
+            return Self::default();
+        }
+        let mean = histogram.weighted_mean();
+        let var = histogram.weighted_variance(mean);
+        let p50 = histogram.value_at_percentile(50.0);
+        let p90 = histogram.value_at_percentile(90.0);
+        let p99 = histogram.value_at_percentile(99.0);
+        let p999 = histogram.value_at_percentile(99.9);
+        let (min, max) = histogram.bounds();
+        let lo = histogram.value_at_percentile(DEFAULT_WINSORIZE_PCT);
+        let hi = histogram.value_at_percentile(100.0 - DEFAULT_WINSORIZE_PCT);
+        let trimmed_mean = histogram.weighted_clamped_mean(lo, hi);
+        let mad = histogram.weighted_mad(p50 as f64);
+        let mad_rel = if p50 > 0 { mad / p50 as f64 } else { 0.0 };
+
+        Self {
+            mean,
+            trimmed_mean,
+            stddev: var.sqrt(),
+            min,
+            max,
+            p50,
+            p90,
+            p99,
+            p999,
+            count: histogram.total as usize,
+            mad,
+            mad_rel,
+            histogram: histogram.clone(),
         }
     }
 
@@ -114,35 +404,472 @@ impl StatResult {
             1e9 / self.trimmed_mean
         }
     }
+
+    /// Recovers any quantile (not just `p50`/`p90`/`p99`/`p999`) from the
+    /// underlying histogram, e.g. `percentile(75.0)`.
+    pub fn percentile(&self, pct: f64) -> u64 {
+        self.histogram.value_at_percentile(pct)
+    }
 }
 
 impl Histogram {
+    pub fn with_config(config: HistogramConfig) -> Self {
+        let geometry = config.resolve();
+        Self {
+            counts: vec![0; geometry.bucket_count],
+            geometry,
+            total: 0,
+        }
+    }
+
+    /// The config this histogram was built with, e.g. to build another empty
+    /// histogram with matching bucket geometry.
+    pub fn config(&self) -> HistogramConfig {
+        self.geometry.config
+    }
+
     pub fn from_samples(samples: &[u64]) -> Self {
-        let mut h = Self::default();
+        Self::from_samples_with_config(samples, HistogramConfig::default())
+    }
+
+    pub fn from_samples_with_config(samples: &[u64], config: HistogramConfig) -> Self {
+        let mut h = Self::with_config(config);
         for &ns in samples {
-            let us = ns / 1000; // ns → μs
-            let bucket = match us {
-                0 => 0,
-                1 => 1,
-                2..=3 => 2,
-                4..=7 => 3,
-                8..=15 => 4,
-                16..=31 => 5,
-                32..=63 => 6,
-                64..=127 => 7,
-                _ => 8,
-            };
-            h.buckets[bucket] += 1;
-            h.total += 1;
+            h.record(ns);
         }
         h
     }
 
+    /// Records a single nanosecond latency sample.
+    pub fn record(&mut self, ns: u64) {
+        self.counts[self.geometry.bucket_index(ns)] += 1;
+        self.total += 1;
+    }
+
+    /// Recovers the value at `pct` (in `[0, 100]`) by walking cumulative
+    /// counts until the target fraction of `total` is reached.
+    pub fn value_at_percentile(&self, pct: f64) -> u64 {
+        if self.total == 0 {
+            return 0;
+        }
+        let target = ((pct / 100.0) * self.total as f64).ceil().max(1.0) as u64;
+        let mut cumulative = 0u64;
+        for (idx, &count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= target {
+                return self.geometry.bucket_value(idx);
+            }
+        }
+        self.geometry.bucket_value(self.counts.len() - 1)
+    }
+
+    /// Folds `other`'s counters into `self`, e.g. to combine per-thread or
+    /// per-run histograms into one distribution. Both histograms must share
+    /// the same [`HistogramConfig`].
+    pub fn merge(&mut self, other: &Histogram) {
+        assert_eq!(
+            self.geometry, other.geometry,
+            "cannot merge histograms with different bucket configs"
+        );
+        for (a, b) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *a += b;
+        }
+        self.total += other.total;
+    }
+
+    /// Adds `n` counts directly to the bucket at `idx`, e.g. when draining
+    /// atomic per-thread counters.
+    fn add(&mut self, idx: usize, n: u64) {
+        self.counts[idx] += n;
+        self.total += n;
+    }
+
+    /// Lowest and highest recorded bucket values. Only as precise as the
+    /// bucket resolution, not the exact samples.
+    fn bounds(&self) -> (u64, u64) {
+        let first = self.counts.iter().position(|&c| c > 0);
+        let last = self.counts.iter().rposition(|&c| c > 0);
+        match (first, last) {
+            (Some(f), Some(l)) => (self.geometry.bucket_value(f), self.geometry.bucket_value(l)),
+            _ => (0, 0),
+        }
+    }
+
+    /// Mean reconstructed from bucket midpoints, since a log histogram
+    /// doesn't retain the exact samples.
+    fn weighted_mean(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &c)| c > 0)
+            .map(|(idx, &c)| self.geometry.bucket_value(idx) as f64 * c as f64)
+            .sum();
+        sum / self.total as f64
+    }
+
+    /// Variance reconstructed from bucket midpoints around `mean`.
+    fn weighted_variance(&self, mean: f64) -> f64 {
+        if self.total <= 1 {
+            return 0.0;
+        }
+        let sum_sq: f64 = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &c)| c > 0)
+            .map(|(idx, &c)| {
+                let d = self.geometry.bucket_value(idx) as f64 - mean;
+                d * d * c as f64
+            })
+            .sum();
+        sum_sq / (self.total as f64 - 1.0)
+    }
+
+    /// Winsorized mean reconstructed from bucket midpoints, clamped to
+    /// `[lo, hi]`.
+    fn weighted_clamped_mean(&self, lo: u64, hi: u64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &c)| c > 0)
+            .map(|(idx, &c)| self.geometry.bucket_value(idx).clamp(lo, hi) as f64 * c as f64)
+            .sum();
+        sum / self.total as f64
+    }
+
+    /// Median absolute deviation reconstructed from bucket midpoints around
+    /// `median`.
+    fn weighted_mad(&self, median: f64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let mut devs: Vec<(f64, u64)> = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &c)| c > 0)
+            .map(|(idx, &c)| ((self.geometry.bucket_value(idx) as f64 - median).abs(), c))
+            .collect();
+        devs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let target = (self.total as f64 / 2.0).ceil().max(1.0) as u64;
+        let mut cumulative = 0u64;
+        let mut mad = 0.0;
+        for (dev, c) in &devs {
+            cumulative += c;
+            if cumulative >= target {
+                mad = *dev;
+                break;
+            }
+        }
+        1.4826 * mad
+    }
+
+    /// Fraction of samples falling in bucket `bucket`, indexed against this
+    /// histogram's own configured edges (see [`HistogramConfig::labels`])
+    /// rather than a fixed table — tune `precision`/`min_ns`/`max_ns` to
+    /// control how many buckets there are and what each one covers.
     pub fn fraction(&self, bucket: usize) -> f64 {
         if self.total == 0 {
             0.0
         } else {
-            self.buckets[bucket] as f64 / self.total as f64
+            self.counts[bucket] as f64 / self.total as f64
+        }
+    }
+
+    /// Human-readable labels for [`Histogram::fraction`]'s buckets.
+    pub fn labels(&self) -> Vec<String> {
+        self.geometry.labels()
+    }
+
+    /// Downsamples the raw (potentially thousands-wide) buckets into
+    /// [`NUM_COARSE_BUCKETS`] fixed log2-µs buckets (see [`COARSE_BUCKET_LABELS`]),
+    /// for the TUI's coarse rendering.
+    fn coarse_counts(&self) -> [u64; NUM_COARSE_BUCKETS] {
+        let mut coarse = [0u64; NUM_COARSE_BUCKETS];
+        for (idx, &count) in self.counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let us = self.geometry.bucket_value(idx) / 1_000;
+            let bucket = COARSE_BOUNDS_US
+                .iter()
+                .position(|&bound| us < bound)
+                .unwrap_or(NUM_COARSE_BUCKETS - 1);
+            coarse[bucket] += count;
+        }
+        coarse
+    }
+
+    /// Fraction of samples falling in coarse display bucket `bucket` (see
+    /// [`COARSE_BUCKET_LABELS`]), regardless of this histogram's own
+    /// [`HistogramConfig`] resolution.
+    pub fn coarse_fraction(&self, bucket: usize) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.coarse_counts()[bucket] as f64 / self.total as f64
+        }
+    }
+}
+
+/// Lock-free per-thread counters, updated with `Ordering::Relaxed` on the
+/// hot recording path so that measuring a latency doesn't perturb it with
+/// allocation or cross-thread contention. Follows the historian/rio design:
+/// each thread owns its counters outright and only synchronizes when
+/// folding them into the shared histogram.
+struct AtomicBuckets {
+    geometry: BucketGeometry,
+    counts: Vec<AtomicU64>,
+}
+
+impl AtomicBuckets {
+    fn new(config: HistogramConfig) -> Self {
+        let geometry = config.resolve();
+        Self {
+            counts: (0..geometry.bucket_count)
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            geometry,
+        }
+    }
+
+    fn record(&self, ns: u64) {
+        self.counts[self.geometry.bucket_index(ns)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Takes each counter's current value, zeroing it, and folds it into
+    /// `target`.
+    fn drain_into(&self, target: &mut Histogram) {
+        for (idx, counter) in self.counts.iter().enumerate() {
+            let n = counter.swap(0, Ordering::Relaxed);
+            if n > 0 {
+                target.add(idx, n);
+            }
+        }
+    }
+}
+
+static RECORDING_CONFIG: OnceLock<HistogramConfig> = OnceLock::new();
+
+/// Configures the resolution used by the module-level [`record`]/[`flush`]/
+/// [`snapshot`] recording path, so a caller can tune it to the workload
+/// under test. Must be called before the first [`record`]/[`flush`]/
+/// [`snapshot`] on any thread — the per-thread counters and the shared
+/// global histogram are both built from whichever config resolves first, so
+/// later calls (or calls after recording has already started with the
+/// default config) have no effect.
+pub fn init(config: HistogramConfig) {
+    let _ = RECORDING_CONFIG.set(config);
+}
+
+fn recording_config() -> HistogramConfig {
+    *RECORDING_CONFIG.get_or_init(HistogramConfig::default)
+}
+
+fn global_histogram() -> &'static Mutex<Histogram> {
+    static GLOBAL: OnceLock<Mutex<Histogram>> = OnceLock::new();
+    GLOBAL.get_or_init(|| Mutex::new(Histogram::with_config(recording_config())))
+}
+
+struct ThreadRecorder {
+    buckets: AtomicBuckets,
+}
+
+impl ThreadRecorder {
+    fn flush(&self) {
+        let mut global = global_histogram().lock().unwrap();
+        self.buckets.drain_into(&mut global);
+    }
+}
+
+impl Drop for ThreadRecorder {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+thread_local! {
+    static RECORDER: ThreadRecorder = ThreadRecorder {
+        buckets: AtomicBuckets::new(recording_config()),
+    };
+}
+
+/// Records a latency sample on this thread's lock-free counters. Cheap and
+/// allocation-free once this thread's counters exist (the first call on a
+/// thread allocates them); call [`flush`] (or let the thread exit) to fold the
+/// counters into the shared histogram that [`snapshot`] reports from.
+pub fn record(ns: u64) {
+    RECORDER.with(|r| r.buckets.record(ns));
+}
+
+/// Folds this thread's counters into the shared global histogram without
+/// waiting for the thread to exit.
+pub fn flush() {
+    RECORDER.with(|r| r.flush());
+}
+
+/// Snapshot of the shared global histogram, combining every thread that has
+/// called [`flush`] or exited so far. Taking a snapshot does not reset it.
+pub fn snapshot() -> Histogram {
+    global_histogram().lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_matches_hand_computed_quantiles() {
+        let sorted: Vec<u64> = (1..=11).collect();
+        assert_eq!(percentile(&sorted, 50.0), 6.0);
+        assert_eq!(percentile(&sorted, 90.0), 10.0);
+        assert_eq!(percentile(&sorted, 99.0), 10.9);
+        assert_eq!(percentile(&sorted, 100.0), 11.0);
+        assert_eq!(percentile(&[42], 50.0), 42.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "winsorize pct must be in [0, 50]")]
+    fn winsorized_mean_rejects_pct_above_fifty() {
+        let samples: Vec<u64> = (1..=100).collect();
+        winsorized_mean(&samples, 60.0);
+    }
+
+    #[test]
+    fn stat_result_percentile_matches_histogram_value_at_percentile() {
+        let mut samples: Vec<u64> = (1..=1000).collect();
+        let result = StatResult::compute(&mut samples);
+        for pct in [10.0, 50.0, 75.0, 90.0, 99.0] {
+            assert_eq!(
+                result.percentile(pct),
+                result.histogram.value_at_percentile(pct)
+            );
+        }
+    }
+
+    #[test]
+    fn histogram_percentile_stays_within_bucket_error_bound() {
+        // Default precision (100.0) bounds relative error to roughly
+        // 1 / (2*precision) = 0.5%; uniform samples let us compare the
+        // recovered percentile directly against the exact rank.
+        let samples: Vec<u64> = (1..=1_000_000).collect();
+        let histogram = Histogram::from_samples(&samples);
+        for (pct, exact) in [(50.0, 500_000.0), (90.0, 900_000.0), (99.0, 990_000.0)] {
+            let recovered = histogram.value_at_percentile(pct) as f64;
+            let rel_error = (recovered - exact).abs() / exact;
+            assert!(
+                rel_error < 0.01,
+                "p{pct} recovered {recovered}, exact {exact}, rel error {rel_error}"
+            );
+        }
+    }
+
+    #[test]
+    fn merge_agrees_with_compute_on_split_samples() {
+        let all: Vec<u64> = (1..=10_000).collect();
+        let whole = StatResult::compute(&mut all.clone());
+
+        let mut first_half: Vec<u64> = all[..5_000].to_vec();
+        let mut second_half: Vec<u64> = all[5_000..].to_vec();
+        let parts = [
+            StatResult::compute(&mut first_half),
+            StatResult::compute(&mut second_half),
+        ];
+        let merged = StatResult::merge(&parts);
+
+        assert_eq!(merged.count, whole.count);
+        assert_eq!(merged.min, whole.min);
+        assert_eq!(merged.max, whole.max);
+        assert!((merged.mean - whole.mean).abs() < 1e-6);
+
+        // Percentiles are recomputed from the merged histogram, so they carry
+        // its usual ~0.5% bucket error relative to the exact rank.
+        for (merged_p, whole_p) in [
+            (merged.p50, whole.p50),
+            (merged.p90, whole.p90),
+            (merged.p99, whole.p99),
+        ] {
+            let rel_error = (merged_p as f64 - whole_p as f64).abs() / whole_p as f64;
+            assert!(
+                rel_error < 0.01,
+                "merged {merged_p}, whole {whole_p}, rel error {rel_error}"
+            );
+        }
+    }
+
+    #[test]
+    fn merge_ignores_zero_count_sub_results() {
+        let mut samples: Vec<u64> = (1_000..=2_000).collect();
+        let real = StatResult::compute(&mut samples);
+        let merged = StatResult::merge(&[real.clone(), StatResult::default()]);
+        assert_eq!(merged.min, 1_000);
+        assert_eq!(merged.max, 2_000);
+    }
+
+    #[test]
+    fn recording_across_threads_accumulates_into_snapshot() {
+        // This exercises the one recording path `record`/`flush`/`snapshot`
+        // don't share with any other test: the lock-free per-thread counters
+        // folding into the shared global histogram. It's the only test in
+        // this module that touches that global state, so it owns it outright.
+        const PER_THREAD: u64 = 1_000;
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                std::thread::spawn(move || {
+                    for i in 0..PER_THREAD {
+                        record(100 + t * PER_THREAD + i);
+                    }
+                    flush();
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
         }
+        let snap = snapshot();
+        assert_eq!(snap.total, 4 * PER_THREAD);
+    }
+
+    #[test]
+    fn merge_handles_non_default_histogram_config() {
+        let custom = HistogramConfig {
+            precision: 20.0,
+            min_ns: 10,
+            max_ns: 1_000_000,
+        };
+        let mut a = Histogram::with_config(custom);
+        let mut b = Histogram::with_config(custom);
+        for ns in 100..200 {
+            a.record(ns);
+        }
+        for ns in 500..600 {
+            b.record(ns);
+        }
+        let results = [
+            StatResult::from_histogram(&a),
+            StatResult::from_histogram(&b),
+        ];
+        let merged = StatResult::merge(&results);
+        assert_eq!(merged.count, 200);
+        assert_eq!(merged.histogram.config(), custom);
+    }
+
+    #[test]
+    fn coarse_fraction_downsamples_into_fixed_buckets() {
+        let mut histogram = Histogram::default();
+        histogram.record(500); // <1us bucket
+        histogram.record(1_500); // 1us bucket
+        histogram.record(200_000_000); // 128+us bucket
+        assert_eq!(histogram.coarse_fraction(0), 1.0 / 3.0);
+        assert_eq!(histogram.coarse_fraction(1), 1.0 / 3.0);
+        assert_eq!(histogram.coarse_fraction(NUM_COARSE_BUCKETS - 1), 1.0 / 3.0);
     }
 }